@@ -0,0 +1,97 @@
+//! Optional TOML configuration.
+//!
+//! Everything here is optional and layered under the CLI flags: a value set
+//! on the command line always wins, and with no config file at all the
+//! built-in defaults apply. Only the small subset of TOML we need is parsed
+//! (flat `key = value` lines whose values are strings or string arrays),
+//! which keeps the crate free of a serializer dependency.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+/// Parsed configuration. Absent keys stay `None`/empty so callers can tell a
+/// missing value from an explicit one and fall back accordingly.
+#[derive(Debug, Default)]
+pub struct Config {
+    pub roots: Vec<PathBuf>,
+    pub dest: Option<PathBuf>,
+    pub video_dest: Option<PathBuf>,
+    pub img_exts: Option<Vec<String>>,
+    pub video_exts: Option<Vec<String>>,
+    pub ignore: Vec<String>,
+}
+
+impl Config {
+    /// The default config location: `$XDG_CONFIG_HOME/dcimv/config.toml`,
+    /// falling back to `$HOME/.config/...`. `None` if neither is set or no
+    /// file exists at that path.
+    pub fn find() -> Option<PathBuf> {
+        let base = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+        let path = base.join("dcimv").join("config.toml");
+        path.exists().then_some(path)
+    }
+
+    /// Parse the config file at `path`.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let mut cfg = Config::default();
+        for line in text.lines() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() || line.starts_with('[') {
+                // Blank lines and section headers are not meaningful here.
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let key = key.trim();
+            let value = value.trim();
+            match key {
+                "roots" => cfg.roots = parse_array(value).into_iter().map(PathBuf::from).collect(),
+                "dest" => cfg.dest = parse_string(value).map(PathBuf::from),
+                "video_dest" => cfg.video_dest = parse_string(value).map(PathBuf::from),
+                "photo_dest" => cfg.dest = parse_string(value).map(PathBuf::from),
+                "img_exts" => cfg.img_exts = Some(parse_array(value)),
+                "video_exts" => cfg.video_exts = Some(parse_array(value)),
+                "ignore" => cfg.ignore = parse_array(value),
+                other => warn!("Unknown config key: {}", other),
+            }
+        }
+        Ok(cfg)
+    }
+}
+
+// Drop a trailing `# comment` that is not inside a quoted string.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_string(value: &str) -> Option<String> {
+    let v = value.trim();
+    let v = v.strip_prefix('"').unwrap_or(v);
+    let v = v.strip_suffix('"').unwrap_or(v);
+    (!v.is_empty()).then(|| v.to_owned())
+}
+
+fn parse_array(value: &str) -> Vec<String> {
+    let inner = value
+        .trim()
+        .strip_prefix('[')
+        .and_then(|v| v.strip_suffix(']'))
+        .unwrap_or(value);
+    inner
+        .split(',')
+        .filter_map(parse_string)
+        .collect()
+}
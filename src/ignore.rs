@@ -0,0 +1,167 @@
+//! A small gitignore-style matcher used to prune watched subdirectories and
+//! to skip individual files.
+//!
+//! Rules are evaluated in order and the last matching pattern wins, so a
+//! later `!pattern` can re-include something an earlier pattern excluded.
+//! Supported syntax mirrors the common subset of gitignore: `*` and `?`
+//! within a path segment, `**` across segments, a leading `/` or an interior
+//! `/` anchors the pattern to the root, a trailing `/` restricts it to
+//! directories, and a leading `!` negates.
+
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug)]
+struct Pattern {
+    negated: bool,
+    dir_only: bool,
+    // Path segments; an unanchored pattern is normalised with a leading `**`.
+    segs: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct IgnoreRules {
+    patterns: Vec<Pattern>,
+}
+
+impl IgnoreRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append every non-empty, non-comment line of an ignore file. A missing
+    /// file is silently ignored, matching how git treats an absent
+    /// `.gitignore`.
+    pub fn load_file(&mut self, path: &Path) {
+        if let Ok(text) = fs::read_to_string(path) {
+            for line in text.lines() {
+                self.add_line(line);
+            }
+        }
+    }
+
+    /// Append a single pattern line (as it would appear in an ignore file).
+    pub fn add_line(&mut self, line: &str) {
+        if let Some(p) = Pattern::parse(line) {
+            self.patterns.push(p);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Whether `path` (relative to the current directory, like every path the
+    /// watcher tracks) should be skipped. Pass
+    /// `is_dir` so directory-only patterns apply correctly.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        // Nothing to match against: skip splitting the path entirely.
+        if self.is_empty() {
+            return false;
+        }
+        let segs: Vec<&str> = path
+            .to_str()
+            .unwrap_or_default()
+            .split('/')
+            .filter(|s| !s.is_empty() && *s != ".")
+            .collect();
+
+        let mut decision = false;
+        for p in &self.patterns {
+            if p.dir_only && !is_dir {
+                continue;
+            }
+            if p.matches(&segs) {
+                decision = !p.negated;
+            }
+        }
+        decision
+    }
+}
+
+impl Pattern {
+    fn parse(line: &str) -> Option<Self> {
+        // Trailing whitespace is insignificant in gitignore.
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let mut s = line;
+        let negated = s.starts_with('!');
+        if negated {
+            s = &s[1..];
+        } else if let Some(rest) = s.strip_prefix('\\') {
+            // Allow escaping a leading `#` or `!`.
+            s = rest;
+        }
+
+        let dir_only = s.ends_with('/');
+        let s = s.trim_end_matches('/');
+
+        // Anchored when the pattern contains a slash other than a trailing one.
+        let anchored = s.contains('/');
+        let s = s.strip_prefix('/').unwrap_or(s);
+
+        let mut segs: Vec<String> = s.split('/').map(str::to_owned).collect();
+        if segs.is_empty() || segs.iter().all(|s| s.is_empty()) {
+            return None;
+        }
+        if !anchored {
+            segs.insert(0, "**".to_owned());
+        }
+
+        Some(Pattern {
+            negated,
+            dir_only,
+            segs,
+        })
+    }
+
+    fn matches(&self, path: &[&str]) -> bool {
+        match_segs(&self.segs, path)
+    }
+}
+
+// Match a list of pattern segments against path segments, where `**` matches
+// zero or more whole segments.
+fn match_segs(pat: &[String], path: &[&str]) -> bool {
+    match pat.split_first() {
+        None => path.is_empty(),
+        Some((head, rest)) => {
+            if head == "**" {
+                (0..=path.len()).any(|i| match_segs(rest, &path[i..]))
+            } else {
+                !path.is_empty()
+                    && match_segment(head.as_bytes(), path[0].as_bytes())
+                    && match_segs(rest, &path[1..])
+            }
+        }
+    }
+}
+
+// Glob one path segment, supporting `*` (any run not crossing `/`) and `?`.
+fn match_segment(pat: &[u8], name: &[u8]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    let (mut star, mut mark) = (None, 0);
+    while n < name.len() {
+        if p < pat.len() && (pat[p] == b'?' || pat[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pat.len() && pat[p] == b'*' {
+            star = Some(p);
+            mark = n;
+            p += 1;
+        } else if let Some(sp) = star {
+            p = sp + 1;
+            mark += 1;
+            n = mark;
+        } else {
+            return false;
+        }
+    }
+    while p < pat.len() && pat[p] == b'*' {
+        p += 1;
+    }
+    p == pat.len()
+}
@@ -1,21 +1,32 @@
-use inotify::{Event, EventMask, Inotify, WatchMask};
 use log::Level;
 use std::cell::{Cell, UnsafeCell};
 use std::collections::{BTreeMap, BTreeSet};
 use std::ffi::{c_int, OsStr, OsString};
 use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf, MAIN_SEPARATOR_STR};
+use std::time::{Duration, Instant};
 use std::{env, fs, io};
 
+use source::{Event, EventSource, Kind};
+
 #[macro_use]
 extern crate log;
 
+mod cas;
+mod config;
+mod ignore;
+mod pool;
+mod source;
+mod sniff;
+
 const CWD: &str = "/sdcard/DCIM";
 
 const IMG_EXT_STRS: [&str; 10] = [
     "jpg", "jpeg", "png", "gif", "webp", "bmp", "tif", "tiff", "jxl", "apng",
 ];
 
+const VIDEO_EXT_STRS: [&str; 2] = ["mp4", "dng"];
+
 struct Twice<T>(UnsafeCell<T>);
 
 unsafe impl<T: Sync> Sync for Twice<T> {}
@@ -27,7 +38,8 @@ impl<T> Twice<T> {
     }
 }
 
-static IMG_EXTS: Twice<BTreeSet<&OsStr>> = Twice(UnsafeCell::new(BTreeSet::new()));
+static IMG_EXTS: Twice<BTreeSet<OsString>> = Twice(UnsafeCell::new(BTreeSet::new()));
+static VIDEO_EXTS: Twice<BTreeSet<OsString>> = Twice(UnsafeCell::new(BTreeSet::new()));
 
 fn is_img_dir(dir: &Path) -> bool {
     dir.components().next().is_some_and(|c| {
@@ -102,43 +114,252 @@ impl Directory {
         }
     }
 
-    pub fn filter_name(&self, name: &Path) -> bool {
-        return name.extension().is_some_and(|ext| {
+    /// Whether a sniffed file warrants moving out of this directory.
+    ///
+    /// The always-move extension class (raw/video, e.g. `dng`/`mp4`) is
+    /// relocated unconditionally even from photo dirs — a `.dng` sniffs as
+    /// TIFF/`Photo`, so the extension check has to win to match the baseline.
+    /// Otherwise videos are always relocated and still images only from
+    /// directories that aren't already a recognised photo location.
+    fn accept(&self, name: &Path, media: sniff::Media) -> bool {
+        if has_video_ext(name) {
+            return true;
+        }
+        match media {
+            sniff::Media::Video => true,
+            sniff::Media::Photo => !self.allow_img,
+        }
+    }
+
+    pub fn filter_name(&self, name: &Path, media: Option<sniff::Media>) -> bool {
+        // Prefer real content when a sniff classified the file; the extension
+        // table is the fallback when sniffing is off, matched no signature, or
+        // the file could not be opened yet (still being written).
+        if let Some(media) = media {
+            return self.accept(name, media);
+        }
+        name.extension().is_some_and(|ext| {
             let ext = ext.to_ascii_lowercase();
-            if ext == "mp4" || ext == "dng" {
-                return true;
-            }
-            if !self.allow_img {
-                unsafe {
+            unsafe {
+                if (*VIDEO_EXTS.get()).contains(ext.as_os_str()) {
+                    return true;
+                }
+                if !self.allow_img {
                     return (*IMG_EXTS.get()).contains(ext.as_os_str());
                 }
             }
             false
-        });
+        })
     }
 }
 
 type Dirs = BTreeMap<c_int, Directory>;
 
+// How long a filtered file must sit untouched before we move it. The media
+// scanner rewrites a freshly created file several times over a few seconds;
+// debouncing coalesces those into a single move.
+const QUIET: Duration = Duration::from_secs(1);
+
+// A filtered file waiting out its quiet period before being moved.
+#[derive(Debug)]
+struct Pending {
+    wd: c_int,
+    name: PathBuf,
+    deadline: Instant,
+    lvl: Level,
+    media: Option<sniff::Media>,
+}
+
+// The move knobs, small and `Copy` so a worker closure can capture them.
+#[derive(Debug, Clone, Copy)]
+struct MoveOpts {
+    dry: bool,
+    link: bool,
+    keep: bool,
+}
+
+// A single move discovered by the recursive scan, ready for the worker pool.
 #[derive(Debug)]
-struct Monitor {
-    inotify: Inotify,
+struct Job {
+    src: PathBuf,
+    dst: PathBuf,
+}
+
+// Android's FUSE doesn't seem to support `RENAME_NOREPLACE` of `renameat2`,
+// so we reserve a destination name with `O_EXCL` to avoid races. Returns
+// `AlreadyExists` when the name is already taken.
+fn reserve(dst: &Path) -> io::Result<()> {
+    drop(
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(dst)?,
+    );
+    Ok(())
+}
+
+// Pick the first free `stem (n).ext` next to `dst`, reserving it.
+fn disambiguate(dst: &Path) -> io::Result<PathBuf> {
+    let parent = dst.parent().unwrap_or_else(|| Path::new(""));
+    let stem = dst.file_stem().unwrap_or_default();
+    let ext = dst.extension();
+    for n in 1.. {
+        let mut name = stem.to_os_string();
+        name.push(format!(" ({})", n));
+        if let Some(ext) = ext {
+            name.push(".");
+            name.push(ext);
+        }
+        let cand = parent.join(name);
+        match reserve(&cand) {
+            Ok(()) => return Ok(cand),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    unreachable!()
+}
+
+// Whether `name`'s extension is in the always-move (raw/video) set.
+fn has_video_ext(name: &Path) -> bool {
+    name.extension().is_some_and(|ext| {
+        let ext = ext.to_ascii_lowercase();
+        unsafe { (*VIDEO_EXTS.get()).contains(ext.as_os_str()) }
+    })
+}
+
+// Whether a file routes to the video destination. The always-move (raw/video)
+// extensions win first, mirroring `Directory::accept`, so a `.dng` routes the
+// same way whether or not sniffing reclassified it; otherwise the sniffed
+// media decides.
+fn is_video(name: &Path, media: Option<sniff::Media>) -> bool {
+    if has_video_ext(name) {
+        return true;
+    }
+    matches!(media, Some(sniff::Media::Video))
+}
+
+// Move `src` to `dst`, resolving a name collision by content hash.
+fn move_into(src: &Path, dst: &Path, opts: MoveOpts, lvl: Level) -> io::Result<()> {
+    if opts.dry {
+        log!(lvl, "Dry run: {} -> {}", src.display(), dst.display());
+        return Ok(());
+    }
+
+    match reserve(dst) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            return resolve_collision(src, dst, opts, lvl);
+        }
+        Err(e) => return Err(e),
+    }
+
+    rename_into(src, dst)?;
+    log!(lvl, "Moved {}", src.display());
+    Ok(())
+}
+
+// Move `src` onto the already-reserved `dst`. A configured `video_dest` may sit
+// on a different mount than the source tree, so fall back to copy+remove when
+// `rename` reports a cross-device link.
+fn rename_into(src: &Path, dst: &Path) -> io::Result<()> {
+    match fs::rename(src, dst) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            fs::copy(src, dst)?;
+            fs::remove_file(src)
+        }
+        r => r,
+    }
+}
+
+// A file already occupies `dst`. If it has the same sampled content id
+// (`cas_id`), the move already happened: drop or keep the source, or replace it
+// with a hard link to the canonical copy. Otherwise land the source under a
+// disambiguated `name (n).ext`.
+fn resolve_collision(src: &Path, dst: &Path, opts: MoveOpts, lvl: Level) -> io::Result<()> {
+    if cas::cas_id(src)? == cas::cas_id(dst)? {
+        if opts.link {
+            // Create the link under a temp name beside the source first, then
+            // swap it in. A cross-device dest fails the `hard_link` before
+            // anything is removed, so the source survives instead of being
+            // deleted with no replacement.
+            let mut tmp = src.as_os_str().to_os_string();
+            tmp.push(".dcimvtmp");
+            let tmp = PathBuf::from(tmp);
+            let _ = fs::remove_file(&tmp);
+            match fs::hard_link(dst, &tmp) {
+                Ok(()) => {
+                    fs::rename(&tmp, src)?;
+                    log!(lvl, "Linked {} -> {}", src.display(), dst.display());
+                }
+                Err(e) => warn!(
+                    "Cannot link {} -> {}: {:?}; keeping source",
+                    src.display(),
+                    dst.display(),
+                    e
+                ),
+            }
+        } else if opts.keep {
+            log!(lvl, "Duplicate {} (kept, matches {})", src.display(), dst.display());
+        } else {
+            // The hash samples only size + three windows, so this can in
+            // theory drop a genuinely different file; warn on the removal.
+            warn!("Duplicate {} (removed, matches {})", src.display(), dst.display());
+            fs::remove_file(src)?;
+        }
+        return Ok(());
+    }
+
+    let alt = disambiguate(dst)?;
+    rename_into(src, &alt)?;
+    log!(lvl, "Moved {} -> {}", src.display(), alt.display());
+    Ok(())
+}
+
+#[derive(Debug)]
+struct Monitor<S: EventSource> {
+    source: S,
     dirs: Dirs,
     inos: BTreeSet<u64>,
     dest: PathBuf,
+    video_dest: Option<PathBuf>,
     dry: bool,
+    sniff: bool,
+    link: bool,
+    keep: bool,
+    ignore: ignore::IgnoreRules,
+    workers: usize,
+    pending: BTreeMap<PathBuf, Pending>,
     fail: bool,
 }
 
-impl Monitor {
-    pub fn new(mut dest: PathBuf, dry: bool) -> Self {
+impl<S: EventSource> Monitor<S> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        source: S,
+        mut dest: PathBuf,
+        video_dest: Option<PathBuf>,
+        dry: bool,
+        sniff: bool,
+        link: bool,
+        keep: bool,
+        ignore: ignore::IgnoreRules,
+        workers: usize,
+    ) -> Self {
         dest.shrink_to_fit();
         Self {
+            source,
             dest,
+            video_dest,
             dry,
-            inotify: Inotify::init().unwrap(),
+            sniff,
+            link,
+            keep,
+            ignore,
+            workers,
             dirs: BTreeMap::new(),
             inos: BTreeSet::new(),
+            pending: BTreeMap::new(),
             fail: false,
         }
     }
@@ -160,18 +381,7 @@ impl Monitor {
             ));
         }
 
-        let wd = self
-            .inotify
-            .watches()
-            .add(
-                &dir,
-                WatchMask::CREATE
-                    | WatchMask::MOVED_TO
-                    | WatchMask::DELETE_SELF
-                    | WatchMask::MOVE_SELF,
-            )
-            .unwrap()
-            .get_watch_descriptor_id();
+        let wd = self.source.add_watch(&dir)?;
 
         let dir = Directory::new(dir, ino);
         debug!("New: {:?}", dir);
@@ -206,6 +416,22 @@ impl Monitor {
     }
 
     pub fn watch(&mut self, root: PathBuf, inplace: bool) {
+        // The recursive walk stays single-threaded for watch-descriptor
+        // bookkeeping; the moves it discovers are run on the worker pool.
+        let mut jobs = Vec::new();
+        self.scan(root, inplace, &mut jobs);
+        if !jobs.is_empty() {
+            info!("Scanning {} files with {} workers", jobs.len(), self.workers);
+            let opts = self.opts();
+            pool::for_each(jobs, self.workers, |job| {
+                if let Err(e) = move_into(&job.src, &job.dst, opts, Level::Warn) {
+                    error!("Error moving {}: {:?}", job.src.display(), e);
+                }
+            });
+        }
+    }
+
+    fn scan(&mut self, root: PathBuf, inplace: bool, jobs: &mut Vec<Job>) {
         // Watch all subdirectories recursively.
         match self.add(root) {
             Ok(wd) => {
@@ -219,23 +445,34 @@ impl Monitor {
 
                     if let Ok(typ) = entry.file_type() {
                         if typ.is_dir() {
-                            if is_ignored_dir(&name, inplace) {
-                                debug!("Ignored: {}", name.to_string_lossy());
+                            let rel = dir.join_owned(name);
+                            if is_ignored_dir(rel.file_name().unwrap(), inplace)
+                                || self.ignore.is_ignored(&rel, true)
+                            {
+                                debug!("Ignored: {}", rel.display());
                             } else {
-                                sub_dirs.push(dir.join_owned(name));
+                                sub_dirs.push(rel);
                             }
                         } else if inplace {
                             let name = Path::new(name.as_os_str());
-                            if dir.filter_name(name) {
-                                if let Err(e) = self.emit(dir, name, Level::Warn) {
-                                    error!("Error moving {}: {:?}", dir.join(name).display(), e);
+                            let src = dir.join(name);
+                            if !self.ignore.is_ignored(&src, false) {
+                                // Sniff once, then reuse it for both the filter
+                                // decision and the video/photo routing.
+                                let media = self.classify(&src);
+                                if dir.filter_name(name, media) {
+                                    // Create the destination now, while single-
+                                    // threaded, then defer the move to the pool.
+                                    let mut dst = self.dest_dir(dir, name, media);
+                                    dst.push(name);
+                                    jobs.push(Job { src, dst });
                                 }
                             }
                         }
                     }
                 }
                 for sub_dir in sub_dirs {
-                    self.watch(sub_dir, inplace);
+                    self.scan(sub_dir, inplace, jobs);
                 }
             }
             Err(e) => error!("Error watching: {:?}", e),
@@ -252,153 +489,355 @@ impl Monitor {
         ))
     }
 
-    fn emit(&self, dir: &Directory, name: &Path, lvl: Level) -> io::Result<()> {
-        let mut dst = dir.dst(&self.dest);
-        let src = dir.join(name);
-        dst.push(name);
-
-        if self.dry {
-            log!(lvl, "Dry run: {} -> {}", src.display(), dst.display());
-            return Ok(());
+    fn opts(&self) -> MoveOpts {
+        MoveOpts {
+            dry: self.dry,
+            link: self.link,
+            keep: self.keep,
         }
+    }
 
-        // Android's FUSE doesn't seem to support `RENAME_NOREPLACE` of
-        // `renameat2`, so we have to avoid races using `O_EXCL`.
-        drop(
-            fs::OpenOptions::new()
-                .write(true)
-                .create_new(true)
-                .open(&dst)?,
-        );
+    // The destination directory for `name`, created if necessary. Videos route
+    // to `video_dest` when one is configured; everything else (and videos when
+    // no split is configured) goes to `dest`.
+    fn dest_dir(&self, dir: &Directory, name: &Path, media: Option<sniff::Media>) -> PathBuf {
+        if let Some(vd) = &self.video_dest {
+            if is_video(name, media) {
+                let r = vd.join(dir.path());
+                let _ = fs::create_dir_all(&r);
+                return r;
+            }
+        }
+        dir.dst(&self.dest)
+    }
 
-        fs::rename(&src, &dst)?;
-        log!(lvl, "Moved {}", src.display());
-        Ok(())
+    fn emit(&self, dir: &Directory, name: &Path, media: Option<sniff::Media>, lvl: Level) -> io::Result<()> {
+        let src = dir.join(name);
+        let mut dst = self.dest_dir(dir, name, media);
+        dst.push(name);
+        move_into(&src, &dst, self.opts(), lvl)
     }
 
-    fn handle_rm(&mut self, e: &Event<&OsStr>) -> io::Result<bool> {
-        if e.mask.contains(EventMask::IGNORED) {
-            // Handle it in another `DELETE_SELF` or `MOVE_SELF` event.
-            return Ok(true);
+    // Classify `src` by content when sniffing is enabled; `None` falls the
+    // callers back to the extension tables. Computing it once here keeps a
+    // filtered file from being opened twice (filter + routing) on FUSE.
+    fn classify(&self, src: &Path) -> Option<sniff::Media> {
+        if !self.sniff {
+            return None;
         }
-
-        if e.mask.contains(EventMask::DELETE_SELF) {
-            // Here `e.name` is `None` and `EventMask::ISDIR` is unset.
-            self.remove(e.wd.get_watch_descriptor_id());
-            return Ok(true);
+        match sniff::sniff(src) {
+            Ok(m) => m,
+            Err(e) => {
+                debug!("Sniff failed for {}: {:?}", src.display(), e);
+                None
+            }
         }
+    }
 
-        if e.mask.contains(EventMask::MOVE_SELF) {
-            self.remove(e.wd.get_watch_descriptor_id());
-            self.inotify.watches().remove(e.wd.clone())?;
-            return Ok(true);
+    fn handle_rm(&mut self, e: &Event) -> io::Result<bool> {
+        match e.kind {
+            // Handle it in another `DeleteSelf` or `MoveSelf` event.
+            Kind::Ignored => Ok(true),
+            Kind::DeleteSelf => {
+                self.remove(e.wd);
+                Ok(true)
+            }
+            Kind::MoveSelf => {
+                self.remove(e.wd);
+                self.source.rm_watch(e.wd)?;
+                Ok(true)
+            }
+            Kind::Appeared => Ok(false),
         }
-
-        Ok(false)
     }
 
-    fn handle(&mut self, e: &Event<&OsStr>, slept: &mut bool) -> io::Result<()> {
-        if let Some(name) = e.name {
+    fn handle(&mut self, e: &Event) -> io::Result<()> {
+        if let Some(name) = &e.name {
             if name.as_encoded_bytes()[0] == b'.' {
                 return Ok(());
             }
 
             let name = Path::new(name);
-            let dir = Self::find_dir(&self.dirs, e.wd.get_watch_descriptor_id())?;
-            if e.mask.contains(EventMask::ISDIR) {
-                self.watch(dir.join(name), false);
+            let wd = e.wd;
+            let dir = Self::find_dir(&self.dirs, wd)?;
+            let isdir = e.is_dir;
+            let src = dir.join(name);
+            if self.ignore.is_ignored(&src, isdir) {
+                debug!("Ignored: {}", src.display());
+                return Ok(());
+            }
+            if isdir {
+                self.watch(src, false);
                 return Ok(());
             }
 
-            if dir.filter_name(name) {
-                // Wait for the gallery to finish media scanning, or
-                // an invalid entry will remain there.
-                if !*slept {
-                    std::thread::sleep(std::time::Duration::from_secs(1));
-                    *slept = true;
-                }
-
-                if let Err(e) = self.emit(dir, name, Level::Info) {
-                    error!("Error moving {}: {:?}", dir.join(name).display(), e);
-                    return Err(e);
-                }
+            let media = self.classify(&src);
+            if dir.filter_name(name, media) {
+                // Don't move yet: the gallery rewrites the file repeatedly
+                // while scanning, so (re)arm its quiet timer and let `run`
+                // move it once it has settled.
+                self.enqueue(src, wd, name.to_path_buf(), Level::Info, media);
             }
         }
         Ok(())
     }
 
-    pub fn run(&mut self, buf: &mut [u8]) {
-        match self.inotify.read_events_blocking(buf) {
-            Ok(events) => {
-                self.fail = false;
-
-                let mut evts = events.collect::<Vec<_>>();
-                debug!("Got {} events: {:?}", evts.len(), evts);
-
-                // Handle removals first to avoid adding duplicate inodes.
-                evts.retain(|evt| match self.handle_rm(evt) {
-                    Ok(true) => false,
-                    Ok(false) => true,
-                    Err(e) => {
-                        error!("Error handling removal: {:?}: {:?}", evt, e);
-                        false
-                    }
-                });
+    // Insert or refresh a file's quiet-period deadline.
+    fn enqueue(
+        &mut self,
+        src: PathBuf,
+        wd: c_int,
+        name: PathBuf,
+        lvl: Level,
+        media: Option<sniff::Media>,
+    ) {
+        let deadline = Instant::now() + QUIET;
+        self.pending.insert(
+            src,
+            Pending {
+                wd,
+                name,
+                deadline,
+                lvl,
+                media,
+            },
+        );
+    }
 
-                let mut slept = false;
-                for evt in evts {
-                    if let Err(e) = self.handle(&evt, &mut slept) {
-                        error!("Error handling: {:?}: {:?}", evt, e);
+    // Move every file whose quiet period has elapsed.
+    fn flush(&mut self) {
+        let now = Instant::now();
+        let ready: Vec<PathBuf> = self
+            .pending
+            .iter()
+            .filter(|(_, p)| p.deadline <= now)
+            .map(|(k, _)| k.clone())
+            .collect();
+
+        for key in ready {
+            let p = self.pending.remove(&key).unwrap();
+            match Self::find_dir(&self.dirs, p.wd) {
+                Ok(dir) => {
+                    if let Err(e) = self.emit(dir, &p.name, p.media, p.lvl) {
+                        error!("Error moving {}: {:?}", key.display(), e);
                     }
                 }
+                // The directory was unwatched while the file was settling.
+                Err(e) => error!("Error moving {}: {:?}", key.display(), e),
             }
-            Err(e) => {
-                if self.fail {
-                    // Don't retry too fast.
-                    panic!("Error reading events again: {:?}", e);
-                } else {
-                    error!("Error reading events: {:?}", e);
-                    self.fail = true;
+        }
+    }
+
+    pub fn run(&mut self, buf: &mut [u8]) {
+        // Wake on new events or when the nearest pending file settles.
+        let timeout = self
+            .pending
+            .values()
+            .map(|p| p.deadline)
+            .min()
+            .map(|d| d.saturating_duration_since(Instant::now()));
+
+        match self.source.poll_readable(timeout) {
+            Ok(true) => match self.source.read(buf) {
+                Ok(mut evts) => {
+                    self.fail = false;
+                    debug!("Got {} events: {:?}", evts.len(), evts);
+
+                    // Handle removals first to avoid adding duplicate inodes.
+                    evts.retain(|evt| match self.handle_rm(evt) {
+                        Ok(true) => false,
+                        Ok(false) => true,
+                        Err(e) => {
+                            error!("Error handling removal: {:?}: {:?}", evt, e);
+                            false
+                        }
+                    });
+
+                    for evt in evts {
+                        if let Err(e) = self.handle(&evt) {
+                            error!("Error handling: {:?}: {:?}", evt, e);
+                        }
+                    }
                 }
-            }
+                Err(e) => self.read_failed(e),
+            },
+            // Timed out: fall through to flush the settled files.
+            Ok(false) => {}
+            Err(e) => self.read_failed(e),
+        }
+
+        self.flush();
+    }
+
+    fn read_failed(&mut self, e: io::Error) {
+        if self.fail {
+            // Don't retry too fast.
+            panic!("Error reading events again: {:?}", e);
+        } else {
+            error!("Error reading events: {:?}", e);
+            self.fail = true;
         }
     }
 }
 
+// Ignore file consulted by default in the current directory.
+const IGNORE_FILE: &str = ".dcimvignore";
+
 struct Args {
     dest: PathBuf,
+    video_dest: Option<PathBuf>,
+    roots: Vec<PathBuf>,
     dry: bool,
     inplace: bool,
+    sniff: bool,
+    link: bool,
+    keep: bool,
+    ignore: ignore::IgnoreRules,
+    workers: usize,
+    config: config::Config,
 }
 
 impl Args {
     fn new() -> Self {
         // Consume `argv[0]` first.
-        let mut args = env::args_os();
-        args.next().unwrap();
+        let argv = env::args_os().skip(1).collect::<Vec<_>>();
+
+        // Load the config file (`-c path`, else the XDG default) up front so
+        // the CLI flags below can override it.
+        let config_path = value_of(&argv, "-c")
+            .map(PathBuf::from)
+            .or_else(config::Config::find);
+        let config = config_path
+            .and_then(|p| match config::Config::load(&p) {
+                Ok(c) => {
+                    info!("Loaded config {}", p.display());
+                    Some(c)
+                }
+                Err(e) => {
+                    error!("Error loading config {}: {:?}", p.display(), e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        let dry = argv.iter().any(|a| a == "-d");
+        let inplace = argv.iter().any(|a| a == "-i");
+        let force = argv.iter().any(|a| a == "-f");
+        let sniff = argv.iter().any(|a| a == "-s");
+        let link = argv.iter().any(|a| a == "-l");
+        let keep = argv.iter().any(|a| a == "-k");
+
+        // Ignore rules: the default ignore file, then config patterns, then
+        // `-I file` files and inline `-x pattern` rules (last wins).
+        let mut ignore = ignore::IgnoreRules::new();
+        ignore.load_file(Path::new(IGNORE_FILE));
+        for pat in &config.ignore {
+            ignore.add_line(pat);
+        }
 
-        // First argument is the destination directory.
-        let dest = PathBuf::from(args.next().unwrap());
+        let mut workers = pool::default_workers();
+        let mut dest_arg = None;
+        let mut i = 0;
+        while i < argv.len() {
+            match argv[i].to_str() {
+                Some("-I") => {
+                    if let Some(f) = argv.get(i + 1) {
+                        ignore.load_file(Path::new(f));
+                    }
+                    i += 1;
+                }
+                Some("-x") => {
+                    if let Some(p) = argv.get(i + 1) {
+                        ignore.add_line(&p.to_string_lossy());
+                    }
+                    i += 1;
+                }
+                Some("-j") => {
+                    if let Some(n) = argv.get(i + 1).and_then(|n| n.to_str()?.parse().ok()) {
+                        workers = n;
+                    }
+                    i += 1;
+                }
+                Some("-c") => i += 1, // already consumed above
+                // A bare word is the destination override.
+                _ if !argv[i].as_encoded_bytes().starts_with(b"-") => {
+                    dest_arg.get_or_insert_with(|| PathBuf::from(&argv[i]));
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+
+        // Destination: CLI positional overrides the config's `dest`.
+        let dest = dest_arg
+            .or_else(|| config.dest.clone())
+            .expect("No destination: pass one as an argument or set `dest` in the config");
         if !fs::metadata(&dest).unwrap().is_dir() {
             panic!("Not a directory: {}", dest.display());
         }
+        let video_dest = config.video_dest.clone();
+
+        // Watch roots from the config, defaulting to the current directory.
+        // The watcher works against cwd-relative paths, so an absolute root is
+        // made relative to cwd (and dropped with a clear error if it lies
+        // outside) rather than panicking deep in `Directory::new`.
+        let cwd = env::current_dir().unwrap();
+        let mut roots: Vec<PathBuf> = config
+            .roots
+            .iter()
+            .filter_map(|r| relativize(r, &cwd))
+            .collect();
+        // Fall back to cwd when no usable root survives — either none was
+        // configured, or every configured root was dropped for lying outside
+        // cwd. Without this the program would silently watch nothing.
+        if roots.is_empty() {
+            roots.push(PathBuf::from("."));
+        }
 
-        let args = args.collect::<Vec<_>>();
-        let dry = args.iter().any(|a| a == "-d");
-        let inplace = args.iter().any(|a| a == "-i");
-        let force = args.iter().any(|a| a == "-f");
+        if !force && fs::metadata(&cwd).unwrap().ino() != fs::metadata(CWD).unwrap().ino() {
+            panic!("Not in {}: {}", CWD, cwd.display());
+        }
 
-        if !force {
-            let cwd = env::current_dir().unwrap();
-            if fs::metadata(&cwd).unwrap().ino() != fs::metadata(CWD).unwrap().ino() {
-                panic!("Not in {}: {}", CWD, cwd.display());
-            }
+        Self {
+            dest,
+            video_dest,
+            roots,
+            dry,
+            inplace,
+            sniff,
+            link,
+            keep,
+            ignore,
+            workers,
+            config,
         }
+    }
+}
 
-        Self { dest, dry, inplace }
+// Resolve a configured watch root to a cwd-relative path. Absolute roots are
+// stripped of the cwd prefix; one outside cwd is reported and dropped.
+fn relativize(root: &Path, cwd: &Path) -> Option<PathBuf> {
+    if root.is_relative() {
+        return Some(root.to_path_buf());
+    }
+    match root.strip_prefix(cwd) {
+        Ok(rel) if rel.as_os_str().is_empty() => Some(PathBuf::from(".")),
+        Ok(rel) => Some(rel.to_path_buf()),
+        Err(_) => {
+            error!("Ignoring root outside {}: {}", cwd.display(), root.display());
+            None
+        }
     }
 }
 
+// First value following `flag` in `argv`, if present.
+fn value_of(argv: &[OsString], flag: &str) -> Option<OsString> {
+    argv.iter()
+        .position(|a| a == flag)
+        .and_then(|i| argv.get(i + 1))
+        .cloned()
+}
+
 fn main() {
     if env::var("RUST_LOG").is_err() {
         env::set_var("RUST_LOG", "info");
@@ -406,20 +845,151 @@ fn main() {
     pretty_env_logger::init_timed();
 
     let args = Args::new();
-    unsafe {
-        for s in IMG_EXT_STRS {
-            (*IMG_EXTS.get()).insert(OsStr::new(s));
-        }
-    }
-
-    let mut m = Monitor::new(args.dest, args.dry);
+    load_exts(args.config.img_exts.as_deref(), args.config.video_exts.as_deref());
+
+    let mut m = Monitor::new(
+        source::InotifySource::new(),
+        args.dest,
+        args.video_dest,
+        args.dry,
+        args.sniff,
+        args.link,
+        args.keep,
+        args.ignore,
+        args.workers,
+    );
     debug!("Monitor: {:?}", m);
 
-    // Always start from the current directory.
-    m.watch(PathBuf::from("."), args.inplace);
+    for root in args.roots {
+        m.watch(root, args.inplace);
+    }
 
     let mut buf = [0; 1024];
     loop {
         m.run(&mut buf);
     }
 }
+
+// Populate the image/video extension tables from config, or the compiled-in
+// defaults when the config leaves a set unspecified.
+fn load_exts(img: Option<&[String]>, video: Option<&[String]>) {
+    let defaults = |set: &Twice<BTreeSet<OsString>>, strs: &[&str]| unsafe {
+        for s in strs {
+            (*set.get()).insert(OsString::from(s.to_ascii_lowercase()));
+        }
+    };
+    let custom = |set: &Twice<BTreeSet<OsString>>, strs: &[String]| unsafe {
+        for s in strs {
+            (*set.get()).insert(OsString::from(s.to_ascii_lowercase()));
+        }
+    };
+    match img {
+        Some(exts) => custom(&IMG_EXTS, exts),
+        None => defaults(&IMG_EXTS, &IMG_EXT_STRS),
+    }
+    match video {
+        Some(exts) => custom(&VIDEO_EXTS, exts),
+        None => defaults(&VIDEO_EXTS, &VIDEO_EXT_STRS),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use source::FakeSource;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Mutex;
+
+    // `Monitor` works against the current directory, which is process-global,
+    // so the cwd-dependent tests run one at a time.
+    static CWD_LOCK: Mutex<()> = Mutex::new(());
+
+    fn scratch() -> PathBuf {
+        static N: AtomicU32 = AtomicU32::new(0);
+        let n = N.fetch_add(1, Ordering::Relaxed);
+        let dir = env::temp_dir().join(format!("dcimv-test-{}-{}", std::process::id(), n));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn monitor(dest: PathBuf) -> Monitor<FakeSource> {
+        // The extension tables are process-wide; populate the defaults.
+        load_exts(None, None);
+        Monitor::new(
+            FakeSource::new(),
+            dest,
+            None,
+            false,
+            false,
+            false,
+            false,
+            ignore::IgnoreRules::new(),
+            1,
+        )
+    }
+
+    #[test]
+    fn settled_create_lands_in_dest() {
+        let _g = CWD_LOCK.lock().unwrap();
+        let work = scratch();
+        let dest = work.join("dest");
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(work.join("IMG_0001.jpg"), b"pretend jpeg").unwrap();
+        env::set_current_dir(&work).unwrap();
+
+        let mut m = monitor(dest.clone());
+        let wd = m.source.add_watch(Path::new(".")).unwrap();
+        let dir = Directory::new(PathBuf::from("."), 1);
+        m.dirs.insert(wd, dir);
+
+        m.source
+            .push_create(wd, OsStr::new("IMG_0001.jpg"), false);
+        m.source.flush();
+
+        let mut buf = [0u8; 256];
+        m.run(&mut buf); // enqueues, nothing moved yet
+        assert!(work.join("IMG_0001.jpg").exists());
+
+        std::thread::sleep(QUIET + Duration::from_millis(100));
+        m.run(&mut buf); // quiet period elapsed: move it
+
+        assert!(dest.join("IMG_0001.jpg").exists());
+        assert!(!work.join("IMG_0001.jpg").exists());
+    }
+
+    #[test]
+    fn move_self_drops_the_watch() {
+        let mut m = monitor(scratch());
+        let wd = m.source.add_watch(Path::new("sub")).unwrap();
+        m.dirs
+            .insert(wd, Directory::new(PathBuf::from("sub"), 42));
+        m.inos.insert(42);
+
+        m.source.push_move_self(wd);
+        m.source.flush();
+
+        let mut buf = [0u8; 256];
+        m.run(&mut buf);
+
+        assert!(!m.dirs.contains_key(&wd));
+        assert!(m.source.watched(wd).is_none());
+    }
+
+    #[test]
+    fn delete_self_drops_the_watch() {
+        let mut m = monitor(scratch());
+        let wd = m.source.add_watch(Path::new("sub")).unwrap();
+        m.dirs
+            .insert(wd, Directory::new(PathBuf::from("sub"), 43));
+        m.inos.insert(43);
+
+        m.source.push_delete_self(wd);
+        m.source.flush();
+
+        let mut buf = [0u8; 256];
+        m.run(&mut buf);
+
+        assert!(!m.dirs.contains_key(&wd));
+        assert!(!m.inos.contains(&43));
+    }
+}
@@ -0,0 +1,48 @@
+//! A minimal bounded worker pool for the initial recursive scan.
+//!
+//! The scan walks the tree on one thread (inotify watch-descriptor
+//! bookkeeping must stay ordered) and hands the per-file move work to a small
+//! fixed set of workers. The cap keeps us from thrashing the SD card's FUSE
+//! layer with thousands of concurrent opens.
+
+use std::sync::Mutex;
+use std::thread;
+
+/// Default upper bound on worker threads.
+pub const MAX_WORKERS: usize = 16;
+
+/// A sensible default worker count: the machine's parallelism, capped.
+pub fn default_workers() -> usize {
+    thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .clamp(1, MAX_WORKERS)
+}
+
+/// Run `f` over every item using up to `workers` threads, blocking until all
+/// items are processed. With a single worker the work runs inline on the
+/// calling thread, avoiding the spawn overhead.
+pub fn for_each<T, F>(items: Vec<T>, workers: usize, f: F)
+where
+    T: Send,
+    F: Fn(T) + Sync,
+{
+    if workers <= 1 {
+        items.into_iter().for_each(f);
+        return;
+    }
+
+    let n = workers.min(items.len()).max(1);
+    let queue = Mutex::new(items.into_iter());
+    thread::scope(|s| {
+        for _ in 0..n {
+            s.spawn(|| loop {
+                let next = queue.lock().unwrap().next();
+                match next {
+                    Some(item) => f(item),
+                    None => break,
+                }
+            });
+        }
+    });
+}
@@ -0,0 +1,84 @@
+//! Magic-byte sniffing to decide a file's media type from its content
+//! rather than trusting the filename extension.
+//!
+//! Cameras and apps routinely emit files with wrong or missing extensions
+//! (a `.jpg` that is really HEIC, a temp name while scanning), so when
+//! sniffing is enabled we peek at the leading bytes and match the common
+//! still-image and video container signatures, falling back to the
+//! extension table when the file cannot be opened yet.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// What the leading bytes of a file look like.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Media {
+    Photo,
+    Video,
+}
+
+// Enough to cover every signature below, including an ISO-BMFF `ftyp` box
+// header plus its major brand.
+const PREFIX: usize = 32;
+
+/// Read the first `PREFIX` bytes of `path` and classify it.
+///
+/// Returns `Ok(None)` when the content matches no known signature; the
+/// caller should then fall back to the extension table. I/O errors
+/// (including the file not existing yet) are propagated so the caller can
+/// decide whether the extension is still trustworthy.
+pub fn sniff(path: &Path) -> io::Result<Option<Media>> {
+    let mut buf = [0u8; PREFIX];
+    let n = File::open(path)?.read(&mut buf)?;
+    Ok(classify(&buf[..n]))
+}
+
+/// Match `b` against the known magic signatures.
+pub fn classify(b: &[u8]) -> Option<Media> {
+    if b.starts_with(&[0xff, 0xd8, 0xff]) {
+        // JPEG, including JFIF/Exif variants.
+        return Some(Media::Photo);
+    }
+    if b.starts_with(&[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]) {
+        // PNG and APNG share the same signature.
+        return Some(Media::Photo);
+    }
+    if b.starts_with(b"GIF87a") || b.starts_with(b"GIF89a") {
+        return Some(Media::Photo);
+    }
+    if b.starts_with(b"BM") {
+        // BMP.
+        return Some(Media::Photo);
+    }
+    if b.starts_with(&[0x49, 0x49, 0x2a, 0x00]) || b.starts_with(&[0x4d, 0x4d, 0x00, 0x2a]) {
+        // TIFF, little- or big-endian. DNG is a TIFF variant.
+        return Some(Media::Photo);
+    }
+    if b.starts_with(&[0xff, 0x0a])
+        || b.starts_with(&[0x00, 0x00, 0x00, 0x0c, b'J', b'X', b'L', b' ', 0x0d, 0x0a, 0x87, 0x0a])
+    {
+        // JPEG XL: raw codestream or ISO-BMFF container.
+        return Some(Media::Photo);
+    }
+    if b.len() >= 12 && &b[..4] == b"RIFF" && &b[8..12] == b"WEBP" {
+        return Some(Media::Photo);
+    }
+    if b.len() >= 12 && &b[4..8] == b"ftyp" {
+        return iso_bmff_brand(&b[8..12]);
+    }
+    None
+}
+
+/// Decide photo- vs video-ness from an ISO-BMFF major brand.
+fn iso_bmff_brand(brand: &[u8]) -> Option<Media> {
+    match brand {
+        // HEIF still images and sequences.
+        b"heic" | b"heix" | b"heim" | b"heis" | b"hevc" | b"hevx" | b"mif1" | b"msf1"
+        | b"avif" => Some(Media::Photo),
+        // MP4 / QuickTime movie brands.
+        b"isom" | b"iso2" | b"mp41" | b"mp42" | b"avc1" | b"M4V " | b"qt  " | b"3gp4"
+        | b"3gp5" => Some(Media::Video),
+        _ => None,
+    }
+}
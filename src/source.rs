@@ -0,0 +1,277 @@
+//! The event source `Monitor` drives itself from.
+//!
+//! The live backend is [`InotifySource`]; [`FakeSource`] is an in-memory
+//! double that replays a scripted queue of events so the move logic can be
+//! exercised deterministically in tests.
+
+use inotify::{Event as InEvent, EventMask, Inotify, WatchMask};
+use std::collections::{BTreeMap, VecDeque};
+use std::ffi::{c_int, OsStr, OsString};
+use std::io;
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// The watch mask we register for every directory.
+const WATCH_MASK: WatchMask = WatchMask::CREATE
+    .union(WatchMask::MOVED_TO)
+    .union(WatchMask::DELETE_SELF)
+    .union(WatchMask::MOVE_SELF);
+
+/// A normalised inotify event, independent of the backing implementation.
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub wd: c_int,
+    pub name: Option<OsString>,
+    pub kind: Kind,
+    pub is_dir: bool,
+}
+
+/// The event flavours the monitor cares about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// An entry was created or moved into a watched directory.
+    Appeared,
+    /// The watched directory itself was deleted.
+    DeleteSelf,
+    /// The watched directory itself was moved away.
+    MoveSelf,
+    /// The kernel dropped the watch; handled via a later self event.
+    Ignored,
+}
+
+/// Something that can watch directories and yield normalised events.
+pub trait EventSource {
+    /// Start watching `path`, returning its watch-descriptor id.
+    fn add_watch(&mut self, path: &Path) -> io::Result<c_int>;
+
+    /// Stop watching the descriptor `wd`.
+    fn rm_watch(&mut self, wd: c_int) -> io::Result<()>;
+
+    /// Block until events are available or `timeout` elapses (`None` blocks
+    /// indefinitely). Returns whether a subsequent [`read`](Self::read) would
+    /// find data.
+    fn poll_readable(&self, timeout: Option<Duration>) -> io::Result<bool>;
+
+    /// Drain the currently available events. `buf` is scratch space for
+    /// backends that need it; may be empty when nothing is pending.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<Vec<Event>>;
+}
+
+/// The live inotify backend.
+#[derive(Debug)]
+pub struct InotifySource {
+    inotify: Inotify,
+    wds: BTreeMap<c_int, inotify::WatchDescriptor>,
+}
+
+impl InotifySource {
+    pub fn new() -> Self {
+        Self {
+            inotify: Inotify::init().unwrap(),
+            wds: BTreeMap::new(),
+        }
+    }
+}
+
+impl EventSource for InotifySource {
+    fn add_watch(&mut self, path: &Path) -> io::Result<c_int> {
+        let wd = self.inotify.watches().add(path, WATCH_MASK)?;
+        let id = wd.get_watch_descriptor_id();
+        self.wds.insert(id, wd);
+        Ok(id)
+    }
+
+    fn rm_watch(&mut self, wd: c_int) -> io::Result<()> {
+        if let Some(w) = self.wds.remove(&wd) {
+            self.inotify.watches().remove(w)?;
+        }
+        Ok(())
+    }
+
+    fn poll_readable(&self, timeout: Option<Duration>) -> io::Result<bool> {
+        let mut fds = libc::pollfd {
+            fd: self.inotify.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let ms = match timeout {
+            Some(d) => d.as_millis().min(c_int::MAX as u128) as c_int,
+            None => -1,
+        };
+        loop {
+            let r = unsafe { libc::poll(&mut fds, 1, ms) };
+            if r < 0 {
+                let e = io::Error::last_os_error();
+                if e.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(e);
+            }
+            return Ok(r > 0);
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<Vec<Event>> {
+        match self.inotify.read_events(buf) {
+            Ok(events) => Ok(events.filter_map(normalize).collect()),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn normalize(e: InEvent<&OsStr>) -> Option<Event> {
+    let m = e.mask;
+    let kind = if m.contains(EventMask::IGNORED) {
+        Kind::Ignored
+    } else if m.contains(EventMask::DELETE_SELF) {
+        Kind::DeleteSelf
+    } else if m.contains(EventMask::MOVE_SELF) {
+        Kind::MoveSelf
+    } else if m.contains(EventMask::CREATE) || m.contains(EventMask::MOVED_TO) {
+        Kind::Appeared
+    } else {
+        return None;
+    };
+    Some(Event {
+        wd: e.wd.get_watch_descriptor_id(),
+        name: e.name.map(OsStr::to_os_string),
+        kind,
+        is_dir: m.contains(EventMask::ISDIR),
+    })
+}
+
+/// An in-memory event source for tests.
+///
+/// Events are staged with the `push_*` helpers and become readable only once
+/// [`flush`](Self::flush) is called, modelling inotify's batching. The
+/// pause/resume switch holds a flush back so a test can script several
+/// batches before any are delivered.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct FakeSource {
+    next_wd: c_int,
+    watches: BTreeMap<c_int, PathBuf>,
+    staging: VecDeque<Event>,
+    buffer: VecDeque<Event>,
+    paused: bool,
+}
+
+#[cfg(test)]
+impl FakeSource {
+    pub fn new() -> Self {
+        Self {
+            next_wd: 1,
+            ..Default::default()
+        }
+    }
+
+    /// The path registered for `wd`, if still watched.
+    pub fn watched(&self, wd: c_int) -> Option<&Path> {
+        self.watches.get(&wd).map(PathBuf::as_path)
+    }
+
+    fn push(&mut self, wd: c_int, name: Option<&OsStr>, kind: Kind, is_dir: bool) {
+        self.staging.push_back(Event {
+            wd,
+            name: name.map(OsStr::to_os_string),
+            kind,
+            is_dir,
+        });
+    }
+
+    /// Script an entry appearing under the directory watched as `wd`.
+    pub fn push_create(&mut self, wd: c_int, name: &OsStr, is_dir: bool) {
+        self.push(wd, Some(name), Kind::Appeared, is_dir);
+    }
+
+    /// Script the watched directory `wd` being deleted.
+    pub fn push_delete_self(&mut self, wd: c_int) {
+        self.push(wd, None, Kind::DeleteSelf, true);
+    }
+
+    /// Script the watched directory `wd` being moved away.
+    pub fn push_move_self(&mut self, wd: c_int) {
+        self.push(wd, None, Kind::MoveSelf, true);
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Make the staged events readable, unless paused.
+    pub fn flush(&mut self) {
+        if !self.paused {
+            self.buffer.append(&mut self.staging);
+        }
+    }
+}
+
+#[cfg(test)]
+impl EventSource for FakeSource {
+    fn add_watch(&mut self, path: &Path) -> io::Result<c_int> {
+        let wd = self.next_wd;
+        self.next_wd += 1;
+        self.watches.insert(wd, path.to_path_buf());
+        Ok(wd)
+    }
+
+    fn rm_watch(&mut self, wd: c_int) -> io::Result<()> {
+        self.watches.remove(&wd);
+        Ok(())
+    }
+
+    fn poll_readable(&self, _timeout: Option<Duration>) -> io::Result<bool> {
+        Ok(!self.buffer.is_empty())
+    }
+
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<Vec<Event>> {
+        Ok(self.buffer.drain(..).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pause_holds_batches_until_resume() {
+        let mut s = FakeSource::new();
+        let wd = s.add_watch(Path::new("dir")).unwrap();
+
+        // First batch, paused: a flush must deliver nothing.
+        s.pause();
+        s.push_create(wd, OsStr::new("a.jpg"), false);
+        s.flush();
+        assert!(!s.poll_readable(None).unwrap());
+
+        // Second batch arrives, then resume + flush releases both at once.
+        s.push_create(wd, OsStr::new("b.jpg"), false);
+        s.resume();
+        s.flush();
+
+        let mut buf = [];
+        let evts = s.read(&mut buf).unwrap();
+        assert_eq!(evts.len(), 2);
+        assert!(evts.iter().all(|e| e.kind == Kind::Appeared));
+    }
+
+    #[test]
+    fn delete_self_is_scripted() {
+        let mut s = FakeSource::new();
+        let wd = s.add_watch(Path::new("dir")).unwrap();
+        s.push_delete_self(wd);
+        s.flush();
+
+        let mut buf = [];
+        let evts = s.read(&mut buf).unwrap();
+        assert_eq!(evts.len(), 1);
+        assert_eq!(evts[0].kind, Kind::DeleteSelf);
+        assert!(evts[0].name.is_none());
+    }
+}